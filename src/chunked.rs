@@ -0,0 +1,203 @@
+use crate::upload::{is_retryable_status, parse_retry_after};
+use anyhow::{Context, Result};
+use reqwest::Client;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc;
+
+/// A single byte range read from a file, ready to be shipped as one
+/// `Content-Range` request.
+pub struct Chunk {
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+/// Reads a file in fixed-size chunks and streams them out over an mpsc
+/// channel, so memory per file stays at a few `chunk_size` buffers
+/// regardless of how large the file is.
+pub struct FileReader {
+    file: File,
+    chunk_size: usize,
+    total: u64,
+}
+
+impl FileReader {
+    pub async fn open(path: &Path, chunk_size: usize) -> Result<Self> {
+        let file = File::open(path)
+            .await
+            .with_context(|| format!("opening {:?} for chunked read", path))?;
+        let total = file
+            .metadata()
+            .await
+            .with_context(|| format!("stat {:?}", path))?
+            .len();
+        Ok(Self {
+            file,
+            chunk_size,
+            total,
+        })
+    }
+
+    pub fn total_len(&self) -> u64 {
+        self.total
+    }
+
+    /// Spawns a task that reads the file chunk by chunk and sends each one
+    /// down the returned channel. The channel capacity of 1 provides the
+    /// backpressure: besides the chunk the sender loop is uploading, at most
+    /// one chunk is queued and one more waits in the reader's pending send,
+    /// next to the reader's reusable `chunk_size` buffer.
+    pub fn into_stream(mut self) -> mpsc::Receiver<std::io::Result<Chunk>> {
+        let (tx, rx) = mpsc::channel(1);
+        tokio::spawn(async move {
+            let mut offset = 0u64;
+            let mut buf = vec![0u8; self.chunk_size];
+            loop {
+                match self.file.read(&mut buf).await {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        let chunk = Chunk {
+                            offset,
+                            data: buf[..n].to_vec(),
+                        };
+                        offset += n as u64;
+                        if tx.send(Ok(chunk)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = tx.send(Err(e)).await;
+                        break;
+                    }
+                }
+            }
+        });
+        rx
+    }
+}
+
+/// Derives a stable upload id for a path so retried/resumed chunk uploads of
+/// the same file reuse the same id across process runs.
+pub fn stable_upload_id(path: &Path) -> String {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Whether a chunk ending at `chunk_end` lies entirely below `resume_from`
+/// and can therefore be skipped on a resumed upload.
+fn should_skip_chunk(chunk_end: u64, resume_from: u64) -> bool {
+    chunk_end <= resume_from
+}
+
+/// Formats the `Content-Range` header value for a chunk spanning
+/// `[offset, offset + len)` out of a file of size `total`.
+fn format_content_range(offset: u64, len: u64, total: u64) -> String {
+    format!("bytes {}-{}/{}", offset, offset + len - 1, total)
+}
+
+/// Outcome of a single failed chunk, mirroring how the whole-file path
+/// (`upload::attempt_whole_file`) distinguishes retryable from fatal
+/// responses so both paths back off/give up consistently.
+pub enum ChunkOutcome {
+    Retryable {
+        offset: u64,
+        msg: String,
+        retry_after: Option<Duration>,
+    },
+    Fatal {
+        msg: String,
+    },
+}
+
+/// Uploads `path` to `url` as a sequence of `Content-Range` POSTs.
+///
+/// `resume_from` skips any chunk fully contained below that offset, so a
+/// caller can restart a previously-failed upload from the failing range
+/// instead of resending the whole file. Returns the outcome of the first
+/// chunk that failed, if any.
+pub async fn upload_chunked(
+    client: &Client,
+    url: &str,
+    path: &Path,
+    chunk_size: usize,
+    upload_id: &str,
+    resume_from: u64,
+    content_type: &str,
+) -> Result<Option<ChunkOutcome>> {
+    let reader = FileReader::open(path, chunk_size).await?;
+    let total = reader.total_len();
+    let mut rx = reader.into_stream();
+
+    while let Some(next) = rx.recv().await {
+        let chunk = next.with_context(|| format!("reading chunk of {:?}", path))?;
+        let chunk_end = chunk.offset + chunk.data.len() as u64;
+        if should_skip_chunk(chunk_end, resume_from) {
+            continue;
+        }
+
+        let content_range = format_content_range(chunk.offset, chunk.data.len() as u64, total);
+        let res = client
+            .post(url)
+            .header("Content-Type", content_type)
+            .header("Content-Range", content_range)
+            .header("X-Upload-Id", upload_id)
+            .body(chunk.data)
+            .send()
+            .await;
+
+        match res {
+            Ok(resp) if resp.status().is_success() => continue,
+            Ok(resp) => {
+                let msg = format!("HTTP {} for chunk at offset {}", resp.status(), chunk.offset);
+                return Ok(Some(if is_retryable_status(resp.status()) {
+                    ChunkOutcome::Retryable {
+                        offset: chunk.offset,
+                        retry_after: parse_retry_after(resp.headers()),
+                        msg,
+                    }
+                } else {
+                    ChunkOutcome::Fatal { msg }
+                }));
+            }
+            Err(e) => {
+                let msg = format!("ERR {} for chunk at offset {}", e, chunk.offset);
+                return Ok(Some(ChunkOutcome::Retryable {
+                    offset: chunk.offset,
+                    msg,
+                    retry_after: None,
+                }));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_content_range_spans_offset_to_inclusive_end() {
+        assert_eq!(format_content_range(0, 100, 250), "bytes 0-99/250");
+        assert_eq!(format_content_range(100, 100, 250), "bytes 100-199/250");
+        assert_eq!(format_content_range(200, 50, 250), "bytes 200-249/250");
+    }
+
+    #[test]
+    fn should_skip_chunk_skips_only_chunks_fully_below_resume_point() {
+        // resume_from falls strictly inside the chunk: still send it.
+        assert!(!should_skip_chunk(100, 50));
+        // chunk ends exactly at resume_from: already covered, skip it.
+        assert!(should_skip_chunk(100, 100));
+        // chunk ends before resume_from: skip it.
+        assert!(should_skip_chunk(100, 150));
+        // nothing uploaded yet: never skip.
+        assert!(!should_skip_chunk(100, 0));
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use tokio::io::AsyncReadExt;
+
+/// Classifies already-in-memory bytes by their leading non-whitespace byte
+/// (`{`/`[` => JSON, `<` => XML), falling back to a generic binary type.
+pub fn sniff_bytes(bytes: &[u8]) -> &'static str {
+    let first = bytes.iter().find(|b| !b.is_ascii_whitespace());
+    match first {
+        Some(b'{') | Some(b'[') => "application/json",
+        Some(b'<') => "application/xml",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Reads just the first few bytes of a file to classify it, for callers
+/// that won't otherwise read the file up front (e.g. chunked uploads).
+/// Prefer `sniff_bytes` when the caller already has the bytes in memory,
+/// to avoid a second open+read of the same file.
+pub async fn sniff(path: &Path) -> Result<&'static str> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("opening {:?} to sniff content type", path))?;
+    let mut buf = [0u8; 64];
+    let n = file
+        .read(&mut buf)
+        .await
+        .with_context(|| format!("reading {:?} to sniff content type", path))?;
+    Ok(sniff_bytes(&buf[..n]))
+}
+
+/// Parses `--content-type-by-ext ext=mime` entries (extension without the
+/// leading dot, case-insensitive) into a lookup map.
+pub fn parse_by_ext(entries: &[String]) -> Result<HashMap<String, String>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let (ext, mime) = entry
+                .split_once('=')
+                .with_context(|| format!("expected ext=mime, got {:?}", entry))?;
+            Ok((ext.trim_start_matches('.').to_lowercase(), mime.to_string()))
+        })
+        .collect()
+}
+
+/// Resolves the Content-Type for `path` from an explicit override or a
+/// `--content-type-by-ext` match on its extension, without touching the
+/// file. Returns `None` when neither applies, meaning the caller still
+/// needs to sniff the file's contents.
+pub fn resolve_static(
+    path: &Path,
+    override_ct: &Option<String>,
+    by_ext: &HashMap<String, String>,
+) -> Option<String> {
+    if let Some(ct) = override_ct {
+        return Some(ct.clone());
+    }
+    path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(|ext| by_ext.get(&ext.to_lowercase()))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_bytes_classifies_json_object_and_array() {
+        assert_eq!(sniff_bytes(b"{\"a\":1}"), "application/json");
+        assert_eq!(sniff_bytes(b"[1,2,3]"), "application/json");
+    }
+
+    #[test]
+    fn sniff_bytes_classifies_xml() {
+        assert_eq!(sniff_bytes(b"<?xml version=\"1.0\"?>"), "application/xml");
+    }
+
+    #[test]
+    fn sniff_bytes_skips_leading_whitespace() {
+        assert_eq!(sniff_bytes(b"   \n\t{\"a\":1}"), "application/json");
+    }
+
+    #[test]
+    fn sniff_bytes_falls_back_to_octet_stream() {
+        assert_eq!(sniff_bytes(b"\x00\x01binary"), "application/octet-stream");
+        assert_eq!(sniff_bytes(b""), "application/octet-stream");
+    }
+
+    #[test]
+    fn parse_by_ext_strips_leading_dot_and_lowercases() {
+        let map = parse_by_ext(&["BOM=application/vnd.cyclonedx+json".to_string()]).unwrap();
+        assert_eq!(map.get("bom").unwrap(), "application/vnd.cyclonedx+json");
+
+        let map = parse_by_ext(&[".xml=application/xml".to_string()]).unwrap();
+        assert_eq!(map.get("xml").unwrap(), "application/xml");
+    }
+
+    #[test]
+    fn parse_by_ext_rejects_entries_without_equals() {
+        assert!(parse_by_ext(&["not-a-mapping".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// How much per-request detail gets echoed to stderr as the run proceeds.
+/// The JSONL log file itself is unaffected by this; it's purely about the
+/// live echo.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/// One structured record per upload, written as a single JSON line once its
+/// retries are done; `attempt` is how many attempts the upload took.
+#[derive(Serialize, serde::Deserialize)]
+pub struct LogEntry {
+    pub path: String,
+    pub status: String,
+    pub bytes: u64,
+    pub duration_ms: f64,
+    pub attempt: u32,
+    pub error: Option<String>,
+}
+
+impl LogEntry {
+    pub fn is_success(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+/// Structured JSONL logger used in place of the old free-form failures.log.
+pub struct Logger {
+    file: Mutex<std::fs::File>,
+    log_success: bool,
+    verbosity: Verbosity,
+}
+
+impl Logger {
+    pub fn open(path: &str, truncate: bool, log_success: bool, verbosity: Verbosity) -> Result<Self> {
+        let mut opts = std::fs::OpenOptions::new();
+        opts.create(true);
+        if truncate {
+            opts.write(true).truncate(true);
+        } else {
+            opts.append(true);
+        }
+        let file = opts.open(path).with_context(|| format!("opening {}", path))?;
+        Ok(Self {
+            file: Mutex::new(file),
+            log_success,
+            verbosity,
+        })
+    }
+
+    /// Records `entry`, writing it to the log file when it's a failure or
+    /// `--log-success` is set, and echoing it to stderr per `--quiet`/`--verbose`.
+    pub fn log(&self, entry: &LogEntry) {
+        let line = serde_json::to_string(entry).expect("LogEntry always serializes");
+
+        if !entry.is_success() || self.log_success {
+            let mut file = self.file.lock().unwrap();
+            writeln!(file, "{}", line).ok();
+        }
+
+        let echo = match self.verbosity {
+            Verbosity::Quiet => false,
+            Verbosity::Normal => !entry.is_success(),
+            Verbosity::Verbose => true,
+        };
+        if echo {
+            eprintln!("{}", line);
+        }
+    }
+}
+
+/// Parses a JSONL log and returns the distinct, sorted set of paths whose
+/// most recent recorded attempt failed, for `--resume`. The log is append-
+/// only and may contain several entries for the same path across runs, so
+/// this keeps only the last entry seen per path before deciding success.
+pub fn read_failed_paths(log_path: &str) -> Result<Vec<std::path::PathBuf>> {
+    let file = std::fs::File::open(log_path)
+        .with_context(|| format!("opening {} to resume from", log_path))?;
+
+    let mut last_success: std::collections::HashMap<String, bool> = std::collections::HashMap::new();
+    for line in std::io::BufRead::lines(std::io::BufReader::new(file)) {
+        let Ok(line) = line else { continue };
+        let Ok(entry) = serde_json::from_str::<LogEntry>(&line) else {
+            continue;
+        };
+        let ok = entry.is_success();
+        last_success.insert(entry.path, ok);
+    }
+
+    let mut paths: Vec<std::path::PathBuf> = last_success
+        .into_iter()
+        .filter(|(_, success)| !success)
+        .map(|(path, _)| std::path::PathBuf::from(path))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
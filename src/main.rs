@@ -4,12 +4,23 @@ use futures::stream::{FuturesUnordered, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
 use reqwest::Client;
 use std::fs;
-use std::io::Write;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Semaphore;
 
+mod chunked;
+mod content_type;
+mod logging;
+mod metrics;
+mod upload;
+
+use logging::{LogEntry, Logger, Verbosity};
+use metrics::Metrics;
+use upload::UploadConfig;
+
+const DEFAULT_LOG_FILE: &str = "failures.log";
+
 /// Fast concurrent uploader for local files
 /// Only runs if the destination host is localhost
 #[derive(Parser, Debug)]
@@ -38,35 +49,201 @@ struct Args {
     /// Request timeout seconds
     #[arg(short = 't', long, default_value_t = 300)]
     timeout_s: u64,
+
+    /// Enable chunked uploads for files larger than --chunk-threshold,
+    /// sending fixed-size byte ranges as sequential Content-Range POSTs
+    /// instead of reading the whole file into memory at once.
+    #[arg(long)]
+    chunk_size: Option<u64>,
+
+    /// Files at or below this size (bytes) are uploaded whole even when
+    /// --chunk-size is set.
+    #[arg(long, default_value_t = 64 * 1024 * 1024)]
+    chunk_threshold: u64,
+
+    /// Number of retries for a retryable outcome (connection error, timeout,
+    /// or HTTP 429/5xx) before giving up on a file.
+    #[arg(long, default_value_t = 5)]
+    max_retries: u32,
+
+    /// Base delay for exponential backoff between retries; actual delay is
+    /// `backoff-base-ms * 2^attempt` plus jitter, or the server's
+    /// Retry-After header when present.
+    #[arg(long, default_value_t = 200)]
+    backoff_base_ms: u64,
+
+    /// Resume a previous run: re-upload only the paths recorded as failed
+    /// in --log-file instead of rescanning --dir, and rewrite the log with
+    /// whatever still fails so repeated runs converge.
+    #[arg(long)]
+    resume: bool,
+
+    /// Walk subdirectories of --dir depth-first instead of scanning only
+    /// the top level.
+    #[arg(long)]
+    recursive: bool,
+
+    /// Only upload files whose path (relative to --dir) matches one of
+    /// these glob patterns, e.g. `--include '*.json'`. A pattern without a
+    /// `/` is matched against the file name alone. May be repeated.
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Skip files whose path (relative to --dir) matches one of these glob
+    /// patterns, with the same file-name rule as --include. Applied after
+    /// --include. May be repeated.
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Print a throughput/latency summary at the end of the run. `json`
+    /// emits the same stats as a single JSON object on stdout instead of
+    /// the human-readable text, so benchmarking scripts can parse it.
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    report: ReportFormat,
+
+    /// Path to the structured JSONL log. Each failed upload writes one line
+    /// once its retries are done: {path, status, bytes, duration_ms,
+    /// attempt, error}. Successful uploads are written only with --log-success.
+    #[arg(long, default_value = DEFAULT_LOG_FILE)]
+    log_file: String,
+
+    /// Also log successful uploads, not just failures.
+    #[arg(long)]
+    log_success: bool,
+
+    /// Don't echo any per-request log lines to stderr.
+    #[arg(long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Echo every per-request log line to stderr, not just failures.
+    #[arg(long, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Use HTTP/2 with prior knowledge instead of HTTP/1.1, so many
+    /// concurrent POSTs multiplex over a small number of TCP connections
+    /// instead of paying per-request connection/handshake overhead. Only
+    /// makes sense against an HTTP/2-capable localhost endpoint.
+    #[arg(long)]
+    http2: bool,
+
+    /// Force this Content-Type for every upload instead of detecting one.
+    #[arg(long)]
+    content_type: Option<String>,
+
+    /// Map a file extension to a Content-Type, e.g. `--content-type-by-ext
+    /// bom=application/vnd.cyclonedx+json`. May be repeated. Checked after
+    /// --content-type and before sniffing the file's leading bytes.
+    #[arg(long)]
+    content_type_by_ext: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum ReportFormat {
+    Text,
+    Json,
+}
+
+/// Prints an informational line to stdout, unless `--report json` is in
+/// effect, in which case it goes to stderr instead so stdout carries only
+/// the JSON summary a benchmarking script would parse.
+macro_rules! info {
+    ($report:expr, $($arg:tt)*) => {
+        if $report == ReportFormat::Json {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Collects files under `dir`, walking subdirectories depth-first when
+/// `recursive` is set. Paths are returned joined onto `dir` (e.g.
+/// `dir/sub/file.json`) so the relative tree structure is preserved
+/// wherever the path is later logged.
+fn collect_files(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut out = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("reading directory {:?}", dir))? {
+        let path = entry.with_context(|| format!("reading entry in {:?}", dir))?.path();
+        if path.is_dir() {
+            if recursive {
+                out.extend(collect_files(&path, recursive)?);
+            }
+        } else if path.is_file() {
+            out.push(path);
+        }
+    }
+    Ok(out)
+}
+
+/// Compiles `--include`/`--exclude` glob patterns up front so invalid
+/// patterns fail fast instead of mid-scan.
+fn compile_patterns(patterns: &[String]) -> Result<Vec<glob::Pattern>> {
+    patterns
+        .iter()
+        .map(|p| glob::Pattern::new(p).with_context(|| format!("invalid glob pattern {:?}", p)))
+        .collect()
+}
+
+/// A pattern without a path separator matches against the file name alone;
+/// one with a separator matches against the whole path relative to --dir.
+fn matches_any(path: &Path, base: &Path, patterns: &[glob::Pattern]) -> bool {
+    let rel = path.strip_prefix(base).unwrap_or(path);
+    patterns.iter().any(|pat| {
+        if pat.as_str().contains('/') {
+            pat.matches_path(rel)
+        } else {
+            rel.file_name()
+                .map(|name| pat.matches(&name.to_string_lossy()))
+                .unwrap_or(false)
+        }
+    })
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
-    // Load all files (any file, no extension filter)
-    let mut entries: Vec<PathBuf> = fs::read_dir(&args.dir)
-        .with_context(|| format!("reading directory {:?}", &args.dir))?
-        .filter_map(|res| res.ok())
-        .map(|e| e.path())
-        .filter(|p| p.is_file())
-        .collect();
+    let mut entries: Vec<PathBuf> = if args.resume {
+        let paths = logging::read_failed_paths(&args.log_file)?;
+        info!(args.report, "Resuming {} previously failed upload(s)", paths.len());
+        paths
+    } else {
+        // Load all files (any file, no extension filter), optionally
+        // recursing into subdirectories and filtering by glob.
+        let include = compile_patterns(&args.include)?;
+        let exclude = compile_patterns(&args.exclude)?;
+        collect_files(&args.dir, args.recursive)?
+            .into_iter()
+            .filter(|p| include.is_empty() || matches_any(p, &args.dir, &include))
+            .filter(|p| !matches_any(p, &args.dir, &exclude))
+            .collect()
+    };
 
     entries.sort();
 
     let total = entries.len();
-    println!("Found {} files in {:?}", total, &args.dir);
+    if !args.resume {
+        info!(args.report, "Found {} files in {:?}", total, &args.dir);
+    }
 
     if total == 0 {
-        println!("No files to upload. Exiting.");
+        info!(args.report, "No files to upload. Exiting.");
         return Ok(());
     }
 
-    let client = Client::builder()
+    let mut client_builder = Client::builder()
         .connect_timeout(Duration::from_secs(args.timeout_s))
         .timeout(Duration::from_secs(args.timeout_s))
-        .pool_max_idle_per_host(args.concurrency)
-        .build()?;
+        .pool_max_idle_per_host(args.concurrency);
+
+    if args.http2 {
+        client_builder = client_builder
+            .http2_prior_knowledge()
+            .http2_initial_stream_window_size(1 << 21)
+            .http2_max_frame_size(1 << 20);
+    }
+
+    let client = client_builder.build()?;
 
     let pb = ProgressBar::new(total as u64);
 
@@ -86,15 +263,35 @@ async fn main() -> Result<()> {
     //     .tick_strings(&["-", "\\", "|", "/"]),
     // );
 
-    // concurrency limiter + shared log file
+    // concurrency limiter + structured log.
+    // --resume starts from a truncated log so repeated runs converge on
+    // whatever still fails, instead of growing forever.
     let semaphore = Arc::new(Semaphore::new(args.concurrency));
-    let failures_file = Arc::new(Mutex::new(
-        std::fs::OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open("failures.log")
-            .context("opening failures.log")?,
-    ));
+    let verbosity = if args.quiet {
+        Verbosity::Quiet
+    } else if args.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    };
+    let logger = Arc::new(Logger::open(
+        &args.log_file,
+        args.resume,
+        args.log_success,
+        verbosity,
+    )?);
+
+    let upload_config = Arc::new(UploadConfig {
+        url: args.url.clone(),
+        chunk_size: args.chunk_size,
+        chunk_threshold: args.chunk_threshold,
+        max_retries: args.max_retries,
+        backoff_base_ms: args.backoff_base_ms,
+        content_type_override: args.content_type.clone(),
+        content_type_by_ext: content_type::parse_by_ext(&args.content_type_by_ext)?,
+    });
+    let metrics = Arc::new(Metrics::new());
+    let run_started = std::time::Instant::now();
 
     // Process in chunks (for memory control)
     for chunk in entries.chunks(args.batch_size) {
@@ -103,55 +300,35 @@ async fn main() -> Result<()> {
         for p in chunk.iter() {
             let path = p.clone();
             let client = client.clone();
-            let url = args.url.clone();
             let permit = semaphore.clone().acquire_owned();
-            let failures_file = failures_file.clone();
+            let logger = logger.clone();
             let pb = pb.clone();
+            let upload_config = upload_config.clone();
+            let metrics = metrics.clone();
 
             // spawn an async task per file
             futures.push(tokio::spawn(async move {
                 let _permit = permit.await;
-                match tokio::fs::read(&path).await {
-                    Ok(bytes) => {
-                        let res = client
-                            .post(&url)
-                            .header("Content-Type", "application/json")
-                            .body(bytes)
-                            .send()
-                            .await;
-                        match res {
-                            Ok(resp) if resp.status().is_success() => {
-                                pb.inc(1);
-                                Ok::<(), (PathBuf, String)>(())
-                            }
-                            Ok(resp) => {
-                                let msg = format!(
-                                    "HTTP {} for {}",
-                                    resp.status().as_u16(),
-                                    path.display()
-                                );
-                                let mut file = failures_file.lock().unwrap();
-                                writeln!(file, "{} | {}", path.display(), msg).ok();
-                                pb.inc(1);
-                                Err((path, msg))
-                            }
-                            Err(e) => {
-                                let msg = format!("ERR {} for {}", e, path.display());
-                                let mut file = failures_file.lock().unwrap();
-                                writeln!(file, "{} | {}", path.display(), msg).ok();
-                                pb.inc(1);
-                                Err((path, msg))
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        let msg = format!("READ_ERR {} for {}", e, path.display());
-                        let mut file = failures_file.lock().unwrap();
-                        writeln!(file, "{} | {}", path.display(), msg).ok();
-                        pb.inc(1);
-                        Err((path, msg))
-                    }
-                }
+
+                let bytes = tokio::fs::metadata(&path).await.map(|m| m.len()).unwrap_or(0);
+                let started = std::time::Instant::now();
+                let upload::UploadResult { attempts, outcome } =
+                    upload::upload_one(&client, &path, &upload_config).await;
+                let elapsed = started.elapsed();
+                let duration_ms = elapsed.as_secs_f64() * 1000.0;
+                metrics.record(bytes, elapsed, outcome.is_ok());
+
+                logger.log(&LogEntry {
+                    path: path.display().to_string(),
+                    status: if outcome.is_ok() { "ok".into() } else { "error".into() },
+                    bytes,
+                    duration_ms,
+                    attempt: attempts,
+                    error: outcome.as_ref().err().map(|(_, msg)| msg.clone()),
+                });
+
+                pb.inc(1);
+                outcome
             }));
         }
 
@@ -164,6 +341,48 @@ async fn main() -> Result<()> {
     }
 
     pb.finish_with_message("Done.");
-    println!("Finished. Check failures.log for any failed uploads.");
+
+    let summary = metrics.summary(run_started.elapsed());
+    match args.report {
+        ReportFormat::Text => {
+            println!("Finished. Check {} for per-upload details.", args.log_file);
+            summary.print_human();
+        }
+        ReportFormat::Json => {
+            println!("{}", serde_json::to_string(&summary)?);
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(p: &str) -> glob::Pattern {
+        glob::Pattern::new(p).unwrap()
+    }
+
+    #[test]
+    fn pattern_without_slash_matches_filename_in_nested_dir() {
+        let base = Path::new("/data");
+        let path = Path::new("/data/sub/report.json");
+        assert!(matches_any(path, base, &[pattern("*.json")]));
+        assert!(!matches_any(path, base, &[pattern("*.xml")]));
+    }
+
+    #[test]
+    fn pattern_with_slash_matches_relative_path_not_just_filename() {
+        let base = Path::new("/data");
+        let path = Path::new("/data/sub/report.json");
+        assert!(matches_any(path, base, &[pattern("sub/*.json")]));
+        assert!(!matches_any(path, base, &[pattern("other/*.json")]));
+    }
+
+    #[test]
+    fn no_patterns_matches_nothing() {
+        let base = Path::new("/data");
+        let path = Path::new("/data/report.json");
+        assert!(!matches_any(path, base, &[]));
+    }
+}
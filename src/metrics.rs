@@ -0,0 +1,127 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+
+struct Sample {
+    duration: Duration,
+    bytes: u64,
+    success: bool,
+}
+
+/// Collects per-file upload durations and byte counts so a summary report
+/// can be printed once the run finishes.
+#[derive(Default)]
+pub struct Metrics {
+    samples: Mutex<Vec<Sample>>,
+}
+
+/// Aggregate stats computed from a `Metrics` collection, also the shape
+/// written out by `--report json`.
+#[derive(Serialize)]
+pub struct Summary {
+    pub total_requests: usize,
+    pub successful: usize,
+    pub failed: usize,
+    pub total_bytes: u64,
+    pub elapsed_secs: f64,
+    pub throughput_mb_s: f64,
+    pub latency_p50_ms: f64,
+    pub latency_p90_ms: f64,
+    pub latency_p99_ms: f64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, bytes: u64, duration: Duration, success: bool) {
+        self.samples.lock().unwrap().push(Sample {
+            duration,
+            bytes,
+            success,
+        });
+    }
+
+    /// Computes the summary. `elapsed` is the wall-clock time of the whole
+    /// run, used for the aggregate throughput figure.
+    pub fn summary(&self, elapsed: Duration) -> Summary {
+        let samples = self.samples.lock().unwrap();
+
+        let mut latencies_ms: Vec<f64> = samples.iter().map(|s| s.duration.as_secs_f64() * 1000.0).collect();
+        latencies_ms.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let successful = samples.iter().filter(|s| s.success).count();
+        let total_bytes: u64 = samples.iter().filter(|s| s.success).map(|s| s.bytes).sum();
+        let elapsed_secs = elapsed.as_secs_f64();
+        let throughput_mb_s = if elapsed_secs > 0.0 {
+            (total_bytes as f64 / 1_000_000.0) / elapsed_secs
+        } else {
+            0.0
+        };
+
+        Summary {
+            total_requests: samples.len(),
+            successful,
+            failed: samples.len() - successful,
+            total_bytes,
+            elapsed_secs,
+            throughput_mb_s,
+            latency_p50_ms: percentile(&latencies_ms, 50.0),
+            latency_p90_ms: percentile(&latencies_ms, 90.0),
+            latency_p99_ms: percentile(&latencies_ms, 99.0),
+        }
+    }
+}
+
+/// Percentile over an already-sorted slice: the element at index
+/// `p / 100 * (len - 1)`, rounded to the nearest index.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0 * (sorted.len() - 1) as f64).round() as usize;
+    sorted[rank.min(sorted.len() - 1)]
+}
+
+impl Summary {
+    pub fn print_human(&self) {
+        println!(
+            "Uploaded {:.2} MB in {:.2}s ({:.2} MB/s) — {} ok, {} failed",
+            self.total_bytes as f64 / 1_000_000.0,
+            self.elapsed_secs,
+            self.throughput_mb_s,
+            self.successful,
+            self.failed,
+        );
+        println!(
+            "Latency p50={:.1}ms p90={:.1}ms p99={:.1}ms",
+            self.latency_p50_ms, self.latency_p90_ms, self.latency_p99_ms
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_of_empty_slice_is_zero() {
+        assert_eq!(percentile(&[], 50.0), 0.0);
+    }
+
+    #[test]
+    fn percentile_of_single_sample_is_that_sample() {
+        assert_eq!(percentile(&[42.0], 99.0), 42.0);
+    }
+
+    #[test]
+    fn percentile_nearest_rank_rounds_to_closest_index() {
+        let sorted = vec![10.0, 20.0, 30.0, 40.0, 50.0];
+        assert_eq!(percentile(&sorted, 0.0), 10.0);
+        assert_eq!(percentile(&sorted, 50.0), 30.0);
+        assert_eq!(percentile(&sorted, 100.0), 50.0);
+        // rank = 0.9 * 4 = 3.6 -> rounds to 4 -> last element
+        assert_eq!(percentile(&sorted, 90.0), 50.0);
+    }
+}
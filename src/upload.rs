@@ -0,0 +1,232 @@
+use crate::chunked;
+use crate::content_type;
+use rand::Rng;
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Per-file settings needed to perform (and retry) an upload.
+#[derive(Clone)]
+pub struct UploadConfig {
+    pub url: String,
+    pub chunk_size: Option<u64>,
+    pub chunk_threshold: u64,
+    pub max_retries: u32,
+    pub backoff_base_ms: u64,
+    pub content_type_override: Option<String>,
+    pub content_type_by_ext: HashMap<String, String>,
+}
+
+enum AttemptOutcome {
+    Success,
+    Retryable {
+        msg: String,
+        retry_after: Option<Duration>,
+    },
+    Fatal {
+        msg: String,
+    },
+}
+
+pub(crate) fn is_retryable_status(status: StatusCode) -> bool {
+    status.as_u16() == 429 || status.is_server_error()
+}
+
+/// Parses a `Retry-After` header in either form the HTTP spec allows: a
+/// number of seconds, or an HTTP-date to wait until.
+pub(crate) fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let deadline = httpdate::parse_http_date(value).ok()?;
+    deadline.duration_since(std::time::SystemTime::now()).ok()
+}
+
+/// Sleeps for `backoff_base_ms * 2^attempt` plus a small random jitter,
+/// unless the server told us exactly how long to wait via `Retry-After`.
+async fn backoff_sleep(attempt: u32, base_ms: u64, retry_after: Option<Duration>) {
+    if let Some(d) = retry_after {
+        tokio::time::sleep(d).await;
+        return;
+    }
+    let exp_ms = base_ms.saturating_mul(1u64 << attempt.min(20));
+    let jitter_ms = rand::thread_rng().gen_range(0..=(exp_ms / 4).max(1));
+    tokio::time::sleep(Duration::from_millis(exp_ms + jitter_ms)).await;
+}
+
+async fn attempt_whole_file(
+    client: &Client,
+    url: &str,
+    path: &Path,
+    static_content_type: &Option<String>,
+) -> AttemptOutcome {
+    match tokio::fs::read(path).await {
+        Ok(bytes) => {
+            // Sniff from the bytes already in hand instead of re-opening
+            // and re-reading the file just to classify it.
+            let content_type = static_content_type
+                .clone()
+                .unwrap_or_else(|| content_type::sniff_bytes(&bytes).to_string());
+            let res = client
+                .post(url)
+                .header("Content-Type", content_type)
+                .body(bytes)
+                .send()
+                .await;
+            match res {
+                Ok(resp) if resp.status().is_success() => AttemptOutcome::Success,
+                Ok(resp) => {
+                    let msg = format!("HTTP {} for {}", resp.status().as_u16(), path.display());
+                    if is_retryable_status(resp.status()) {
+                        AttemptOutcome::Retryable {
+                            msg,
+                            retry_after: parse_retry_after(resp.headers()),
+                        }
+                    } else {
+                        AttemptOutcome::Fatal { msg }
+                    }
+                }
+                Err(e) => AttemptOutcome::Retryable {
+                    msg: format!("ERR {} for {}", e, path.display()),
+                    retry_after: None,
+                },
+            }
+        }
+        Err(e) => AttemptOutcome::Fatal {
+            msg: format!("READ_ERR {} for {}", e, path.display()),
+        },
+    }
+}
+
+async fn attempt_chunked(
+    client: &Client,
+    url: &str,
+    path: &Path,
+    chunk_size: usize,
+    upload_id: &str,
+    resume_from: &mut u64,
+    content_type: &str,
+) -> AttemptOutcome {
+    match chunked::upload_chunked(
+        client,
+        url,
+        path,
+        chunk_size,
+        upload_id,
+        *resume_from,
+        content_type,
+    )
+    .await
+    {
+        Ok(None) => AttemptOutcome::Success,
+        Ok(Some(chunked::ChunkOutcome::Retryable {
+            offset,
+            msg,
+            retry_after,
+        })) => {
+            *resume_from = offset;
+            AttemptOutcome::Retryable { msg, retry_after }
+        }
+        Ok(Some(chunked::ChunkOutcome::Fatal { msg })) => AttemptOutcome::Fatal { msg },
+        Err(e) => AttemptOutcome::Fatal {
+            msg: format!("{:#}", e),
+        },
+    }
+}
+
+/// Outcome of `upload_one`: the final result plus how many attempts it took
+/// (1 means it succeeded or failed fatally on the first try).
+pub struct UploadResult {
+    pub attempts: u32,
+    pub outcome: Result<(), (PathBuf, String)>,
+}
+
+/// Uploads a single file, retrying retryable outcomes (connection errors,
+/// timeouts, HTTP 429/5xx) with exponential backoff up to `max_retries`
+/// times. Chunked uploads resume from the last failing offset on retry
+/// rather than restarting the whole file.
+pub async fn upload_one(client: &Client, path: &Path, config: &UploadConfig) -> UploadResult {
+    let use_chunked = match config.chunk_size {
+        Some(_) => tokio::fs::metadata(path)
+            .await
+            .map(|m| m.len() > config.chunk_threshold)
+            .unwrap_or(false),
+        None => false,
+    };
+    let upload_id = chunked::stable_upload_id(path);
+    let mut resume_from = 0u64;
+
+    // An override or extension match needs no file access; only sniffing
+    // does. The chunked reader never hands us the whole file, so resolve
+    // its content type eagerly (at the cost of one small peek read);
+    // the whole-file path resolves it lazily from the bytes it already
+    // reads, to avoid a second open+read per file.
+    let static_ct = content_type::resolve_static(
+        path,
+        &config.content_type_override,
+        &config.content_type_by_ext,
+    );
+    let chunked_ct = if use_chunked {
+        match &static_ct {
+            Some(ct) => ct.clone(),
+            None => match content_type::sniff(path).await {
+                Ok(ct) => ct.to_string(),
+                Err(e) => {
+                    return UploadResult {
+                        attempts: 1,
+                        outcome: Err((path.to_path_buf(), format!("{:#}", e))),
+                    }
+                }
+            },
+        }
+    } else {
+        String::new()
+    };
+
+    for attempt in 0..=config.max_retries {
+        let outcome = if use_chunked {
+            attempt_chunked(
+                client,
+                &config.url,
+                path,
+                config.chunk_size.unwrap() as usize,
+                &upload_id,
+                &mut resume_from,
+                &chunked_ct,
+            )
+            .await
+        } else {
+            attempt_whole_file(client, &config.url, path, &static_ct).await
+        };
+
+        match outcome {
+            AttemptOutcome::Success => {
+                return UploadResult {
+                    attempts: attempt + 1,
+                    outcome: Ok(()),
+                }
+            }
+            AttemptOutcome::Fatal { msg } => {
+                return UploadResult {
+                    attempts: attempt + 1,
+                    outcome: Err((path.to_path_buf(), msg)),
+                }
+            }
+            AttemptOutcome::Retryable { msg, retry_after } => {
+                if attempt == config.max_retries {
+                    return UploadResult {
+                        attempts: attempt + 1,
+                        outcome: Err((path.to_path_buf(), msg)),
+                    };
+                }
+                backoff_sleep(attempt, config.backoff_base_ms, retry_after).await;
+            }
+        }
+    }
+
+    unreachable!("loop always returns by the time attempt == max_retries")
+}